@@ -0,0 +1,53 @@
+//! Demonstrates the allocation savings from `SwapIoClmmAdapter::update()`
+//! borrowing tick-array accounts out of the `AccountMap` (via
+//! `jupiter_swap_io_adapter::borrow_accounts`) instead of cloning them.
+
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jupiter_swap_io_adapter::borrow_accounts;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use swap_io_clmm_rust_sdk::pool::NEIGHBORHOOD_SIZE;
+
+const TICK_ARRAY_ACCOUNT_LEN: usize = 10_240;
+
+fn fixture_account_map(count: usize) -> HashMap<Pubkey, Account> {
+    (0..count)
+        .map(|_| {
+            let account = Account {
+                lamports: 1,
+                data: vec![0u8; TICK_ARRAY_ACCOUNT_LEN],
+                owner: Pubkey::new_unique(),
+                executable: false,
+                rent_epoch: 0,
+            };
+            (Pubkey::new_unique(), account)
+        })
+        .collect()
+}
+
+/// What `get_tick_arrays_accounts` did before this request: clone every
+/// tick-array `Account` out of the map on each refresh.
+fn clone_tick_arrays(keys: &[Pubkey], account_map: &HashMap<Pubkey, Account>) -> Vec<Account> {
+    keys.iter()
+        .map(|key| account_map.get(key).expect("fixture account present").clone())
+        .collect()
+}
+
+fn bench_tick_array_refresh(c: &mut Criterion) {
+    // Both sides of the recentered window, at the real neighborhood size.
+    let account_map = fixture_account_map(NEIGHBORHOOD_SIZE as usize * 2);
+    let keys: Vec<Pubkey> = account_map.keys().copied().collect();
+
+    let mut group = c.benchmark_group("tick_array_refresh");
+    group.bench_function("clone_per_update (before)", |b| {
+        b.iter(|| black_box(clone_tick_arrays(&keys, &account_map)))
+    });
+    group.bench_function("borrow_accounts (after)", |b| {
+        b.iter(|| black_box(borrow_accounts(&keys, &account_map).expect("fixture keys present")))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_tick_array_refresh);
+criterion_main!(benches);
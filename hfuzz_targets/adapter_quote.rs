@@ -0,0 +1,18 @@
+use arbitrary::{Arbitrary, Unstructured};
+use honggfuzz::fuzz;
+use jupiter_swap_io_adapter::fuzz::FuzzSwap;
+
+fn main() {
+    loop {
+        fuzz!(|data: &[u8]| {
+            let mut unstructured = Unstructured::new(data);
+            let Ok(input) = FuzzSwap::arbitrary(&mut unstructured) else {
+                return;
+            };
+
+            if let Err(message) = jupiter_swap_io_adapter::fuzz::check_invariants(input) {
+                panic!("{message}");
+            }
+        });
+    }
+}
@@ -1,29 +1,194 @@
 
+use std::collections::HashSet;
+use std::sync::Mutex;
+
 use anyhow::Result;
 use jupiter_amm_interface::{
-    AccountMap, Amm, AmmContext, KeyedAccount, Quote, QuoteParams, Swap, SwapAndAccountMetas, SwapParams
+    AccountMap, Amm, AmmContext, AmmUserSetup, KeyedAccount, Quote, QuoteParams, Swap, SwapAndAccountMetas, SwapParams
 };
 use solana_sdk::{account::Account, pubkey::Pubkey};
-// use swap_io_clmm::states::{AmmConfig, TickArrayBitmapExtension, TickArrayState};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use swap_io_clmm::states::TickArrayBitmapExtension;
+use spl_token_2022::{
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+    state::Mint,
+};
 use swap_io_clmm_rust_sdk::{instruction::InstructionBuilder, pool::{PoolManager, NEIGHBORHOOD_SIZE}, quote::QuoteCalculator};
+use swap_io_clmm::states::tick_math::{MIN_TICK, MAX_TICK};
 
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
 
+/// Token-2022 transfer fees a `quote()` applied on top of the pool's own
+/// swap fee, separated out so callers can distinguish the two.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransferFeeBreakdown {
+    pub input_transfer_fee: u64,
+    pub output_transfer_fee: u64,
+}
 
-#[derive(Clone)]
 pub struct SwapIoClmmAdapter {
     pool_manager: PoolManager,
+    mint0_account: Option<Account>,
+    mint1_account: Option<Account>,
+    // Tick arrays confirmed present in the account map as of the last
+    // `update()`, used by `get_swap_and_account_metas` to tell an
+    // already-initialized tick array from one the swap would be crossing
+    // into for the first time.
+    known_tick_arrays: HashSet<Pubkey>,
+    // Setup instructions computed by the most recent `get_swap_and_account_metas`
+    // call, handed back out through `get_user_setup`. A `Mutex` (rather than a
+    // `RefCell`) because `Amm` implementors must stay `Send + Sync` --
+    // `clone_amm` returns `Box<dyn Amm + Send + Sync>`.
+    pending_user_setup: Mutex<Option<AmmUserSetup>>,
+}
+
+impl Clone for SwapIoClmmAdapter {
+    fn clone(&self) -> Self {
+        // `pending_user_setup` is scratch state for a single
+        // get_swap_and_account_metas/get_user_setup pair; a fresh clone
+        // hasn't made that call yet, so it starts empty rather than copying
+        // whatever the source happened to have pending.
+        Self {
+            pool_manager: self.pool_manager.clone(),
+            mint0_account: self.mint0_account.clone(),
+            mint1_account: self.mint1_account.clone(),
+            known_tick_arrays: self.known_tick_arrays.clone(),
+            pending_user_setup: Mutex::new(None),
+        }
+    }
 }
 
 impl SwapIoClmmAdapter {
     fn new(pool_key: Pubkey, pool_state_account: &Account, program_id: Pubkey, epoch: u64) -> Result<Self> {
         let pool_manager = PoolManager::new(epoch, pool_key, program_id, pool_state_account)?;
 
+        if pool_manager.pool_state.tick_spacing == 0 {
+            return Err(anyhow::anyhow!(
+                "invalid tick_spacing 0 for pool {pool_key}: pool is misconfigured"
+            ));
+        }
+
         Ok(
         Self {
             pool_manager,
+            mint0_account: None,
+            mint1_account: None,
+            known_tick_arrays: HashSet::new(),
+            pending_user_setup: Mutex::new(None),
         })
     }
 
+    /// Returns the Token-2022 `TransferFeeConfig` extension for `mint`, if the
+    /// mint carries one. `mint` must be one of the pool's two reserve mints,
+    /// since those are the only mint accounts the adapter keeps around.
+    fn transfer_fee_config(&self, mint: Pubkey) -> Result<Option<TransferFeeConfig>> {
+        let mint_account = if mint == self.pool_manager.pool_state.token_mint_0 {
+            self.mint0_account.as_ref()
+        } else if mint == self.pool_manager.pool_state.token_mint_1 {
+            self.mint1_account.as_ref()
+        } else {
+            return Ok(None);
+        };
+
+        let Some(mint_account) = mint_account else {
+            return Ok(None);
+        };
+
+        let mint_state = StateWithExtensions::<Mint>::unpack(&mint_account.data)?;
+        match mint_state.get_extension::<TransferFeeConfig>() {
+            Ok(config) => Ok(Some(*config)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Fee withheld by `mint`'s Token-2022 transfer-fee extension when
+    /// `amount` is transferred, using the fee rate active at `epoch`.
+    /// Returns `0` for mints without the extension.
+    fn transfer_fee_for_amount(&self, mint: Pubkey, epoch: u64, amount: u64) -> Result<u64> {
+        Ok(self
+            .transfer_fee_config(mint)?
+            .and_then(|config| config.calculate_epoch_fee(epoch, amount))
+            .unwrap_or(0))
+    }
+
+    /// Pre-fee amount that nets out to `net_amount` after `mint`'s
+    /// Token-2022 transfer fee is withheld, at the fee rate active at
+    /// `epoch`. Returns `net_amount` unchanged for mints without the
+    /// extension.
+    fn gross_up_for_transfer_fee(&self, mint: Pubkey, epoch: u64, net_amount: u64) -> Result<u64> {
+        Ok(self
+            .transfer_fee_config(mint)?
+            .and_then(|config| config.calculate_inverse_epoch_fee(epoch, net_amount))
+            .map(|fee| net_amount.saturating_add(fee))
+            .unwrap_or(net_amount))
+    }
+
+    /// Same as `Amm::quote`, but also returns the Token-2022 transfer fees
+    /// that were applied on top of the pool's own swap fee, so callers that
+    /// need the breakdown don't have to re-derive it from shared state --
+    /// `quote(&self)` can be called concurrently, so stashing "the last
+    /// call's" fees in `self` would be racy.
+    pub fn quote_with_transfer_fees(&self, quote_params: &QuoteParams) -> Result<(Quote, TransferFeeBreakdown)> {
+        let epoch = self.pool_manager.epoch();
+        let exact_in = quote_params.swap_mode == jupiter_amm_interface::SwapMode::ExactIn;
+
+        if exact_in {
+            let input_transfer_fee =
+                self.transfer_fee_for_amount(quote_params.input_mint, epoch, quote_params.amount)?;
+            let amount_after_input_fee = quote_params.amount.saturating_sub(input_transfer_fee);
+
+            let quote = QuoteCalculator::calculate_quote(
+                quote_params.input_mint,
+                quote_params.output_mint,
+                true,
+                amount_after_input_fee,
+                &self.pool_manager)?;
+
+            let output_transfer_fee =
+                self.transfer_fee_for_amount(quote_params.output_mint, epoch, quote.out_amount)?;
+            let out_amount = quote.out_amount.saturating_sub(output_transfer_fee);
+
+            Ok((
+                Quote {
+                    fee_pct: quote.fee_pct,
+                    in_amount: quote_params.amount,
+                    out_amount,
+                    fee_amount: quote.fee_amount,
+                    fee_mint: quote.fee_mint,
+                    ..Quote::default()
+                },
+                TransferFeeBreakdown { input_transfer_fee, output_transfer_fee },
+            ))
+        } else {
+            let target_out_with_fee =
+                self.gross_up_for_transfer_fee(quote_params.output_mint, epoch, quote_params.amount)?;
+
+            let quote = QuoteCalculator::calculate_quote(
+                quote_params.input_mint,
+                quote_params.output_mint,
+                false,
+                target_out_with_fee,
+                &self.pool_manager)?;
+
+            let in_amount = self.gross_up_for_transfer_fee(quote_params.input_mint, epoch, quote.in_amount)?;
+            let input_transfer_fee = in_amount.saturating_sub(quote.in_amount);
+            let output_transfer_fee = target_out_with_fee.saturating_sub(quote_params.amount);
+
+            Ok((
+                Quote {
+                    fee_pct: quote.fee_pct,
+                    in_amount,
+                    out_amount: quote_params.amount,
+                    fee_amount: quote.fee_amount,
+                    fee_mint: quote.fee_mint,
+                    ..Quote::default()
+                },
+                TransferFeeBreakdown { input_transfer_fee, output_transfer_fee },
+            ))
+        }
+    }
+
     pub fn get_up_tick_array_keys(&self) -> &Vec<Pubkey> {
         &self.pool_manager.up_tick_array_keys
     }
@@ -35,16 +200,141 @@ impl SwapIoClmmAdapter {
         &self.pool_manager
     }
 
-    fn get_tick_arrays_accounts(&self, tick_array_keys: &Vec<Pubkey>, account_map: &AccountMap) -> Result<Vec<Account>> {
-        let mut tick_arrays = vec![];
-        for key in tick_array_keys.iter() {
-            let tick_array_account = account_map
-                .get(key)
-                .ok_or_else(|| anyhow::anyhow!("TickArray account not found"))?;
-            tick_arrays.push(tick_array_account.clone());
+    /// Recomputes `up_tick_array_keys`/`down_tick_array_keys` so they're
+    /// centered on the pool's current tick, walking the main bitmap plus
+    /// `tick_array_bitmap_extension` in both directions. Called after every
+    /// `update()` so the next `get_accounts_to_update()` asks the host for
+    /// whatever arrays the current price region actually needs.
+    fn recenter_tick_arrays(&mut self, tick_array_bitmap_extension_account: &Account) -> Result<()> {
+        let bitmap_extension =
+            TickArrayBitmapExtension::deserialize(&mut tick_array_bitmap_extension_account.data.as_slice())?;
+        let pool_state = &self.pool_manager.pool_state;
+        let tick_spacing = pool_state.tick_spacing;
+
+        self.pool_manager.up_tick_array_keys = Self::walk_tick_array_starts(
+            pool_state.tick_current,
+            tick_spacing,
+            pool_state.tick_array_bitmap,
+            &bitmap_extension,
+            false,
+        )
+        .into_iter()
+        .map(|start_index| self.pool_manager.tick_array_key(start_index))
+        .collect();
+
+        self.pool_manager.down_tick_array_keys = Self::walk_tick_array_starts(
+            pool_state.tick_current,
+            tick_spacing,
+            pool_state.tick_array_bitmap,
+            &bitmap_extension,
+            true,
+        )
+        .into_iter()
+        .map(|start_index| self.pool_manager.tick_array_key(start_index))
+        .collect();
+
+        Ok(())
+    }
+
+    /// Collects up to `NEIGHBORHOOD_SIZE` initialized tick-array start
+    /// indices in `zero_for_one`'s direction from `tick_current`, skipping
+    /// uninitialized bitmap words and truncating near the tick boundary
+    /// instead of fabricating out-of-range indices. Tick-range-agnostic: the
+    /// boundary check is against the pool's actual `[MIN_TICK, MAX_TICK]`
+    /// domain rather than an assumption baked in for the narrow tick range.
+    ///
+    /// `next_initialized_tick_array_from_one_position` is exclusive of the
+    /// array passed in, so the array containing `tick_current` itself -- the
+    /// one holding the liquidity actively in range right now -- is pushed
+    /// unconditionally before walking outward; otherwise it would never
+    /// appear in either the up or down window.
+    fn walk_tick_array_starts(
+        tick_current: i32,
+        tick_spacing: u16,
+        tick_array_bitmap: [u64; 16],
+        bitmap_extension: &TickArrayBitmapExtension,
+        zero_for_one: bool,
+    ) -> Vec<i32> {
+        let mut starts = Vec::with_capacity(NEIGHBORHOOD_SIZE as usize);
+        let tick_current = tick_current.clamp(MIN_TICK, MAX_TICK);
+        let current_start_index = TickArrayBitmapExtension::get_array_start_index(tick_current, tick_spacing);
+
+        starts.push(current_start_index);
+        let mut last_start_index = current_start_index;
+
+        while starts.len() < NEIGHBORHOOD_SIZE as usize {
+            let next = bitmap_extension
+                .next_initialized_tick_array_from_one_position(
+                    last_start_index,
+                    tick_spacing,
+                    zero_for_one,
+                    &tick_array_bitmap,
+                )
+                .ok()
+                .flatten();
+
+            let Some(next_start_index) = next else {
+                break;
+            };
+            if next_start_index < MIN_TICK || next_start_index > MAX_TICK {
+                break;
+            }
+            starts.push(next_start_index);
+            last_start_index = next_start_index;
         }
-        Ok(tick_arrays)
+
+        starts
+    }
+
+    /// Number of tick arrays needed, per side, to cover the same price
+    /// movement a dense `NEIGHBORHOOD_SIZE`-array window covers at the
+    /// reference (finest) tick spacing. A single tick array spans
+    /// `tick_spacing * TICK_ARRAY_SIZE` ticks, so coarser spacings need
+    /// fewer arrays to span the same range.
+    fn tick_arrays_needed_for_spacing(tick_spacing: u16) -> usize {
+        const REFERENCE_TICK_SPACING: usize = 1;
+        const MIN_TICK_ARRAY_ACCOUNTS: usize = 2;
+
+        let scale = (tick_spacing as usize).max(REFERENCE_TICK_SPACING);
+        let scaled = (NEIGHBORHOOD_SIZE as usize * REFERENCE_TICK_SPACING).div_ceil(scale);
+        scaled.clamp(MIN_TICK_ARRAY_ACCOUNTS, NEIGHBORHOOD_SIZE as usize)
+    }
+
+    /// Whether `account` looks like an already-initialized tick array on
+    /// chain, rather than an empty placeholder the RPC returns for a key
+    /// that doesn't exist yet. Accounts that don't exist come back with zero
+    /// lamports (and no owner change from the system program), so lamports
+    /// and ownership are what distinguish "really there" from "just in my
+    /// fetch window."
+    fn is_tick_array_initialized(&self, account: &Account) -> bool {
+        account.lamports > 0
+            && account.owner == self.pool_manager.program_id
+            && account.data.len() >= 8
+    }
+
+    /// Borrows (rather than clones) the tick-array accounts for `tick_array_keys`
+    /// straight out of `account_map`, so a refresh doesn't allocate a fresh
+    /// `Account` per tick array on every update. Thin wrapper around
+    /// `borrow_accounts` so the same code path is what `benches/account_update`
+    /// measures.
+    fn get_tick_arrays_accounts<'a>(&self, tick_array_keys: &Vec<Pubkey>, account_map: &'a AccountMap) -> Result<Vec<&'a Account>> {
+        borrow_accounts(tick_array_keys, account_map)
+    }
+}
+
+/// Borrows the accounts for `keys` straight out of `account_map` instead of
+/// cloning them. Exposed at the crate root (rather than kept private on
+/// `SwapIoClmmAdapter`) so `benches/account_update.rs` exercises the exact
+/// code path `update()` runs on every refresh.
+pub fn borrow_accounts<'a>(keys: &[Pubkey], account_map: &'a AccountMap) -> Result<Vec<&'a Account>> {
+    let mut accounts = Vec::with_capacity(keys.len());
+    for key in keys {
+        let account = account_map
+            .get(key)
+            .ok_or_else(|| anyhow::anyhow!("account not found"))?;
+        accounts.push(account);
     }
+    Ok(accounts)
 }
 
 impl Amm for SwapIoClmmAdapter
@@ -119,30 +409,86 @@ where
         
         let up_ticks_accounts = self.get_tick_arrays_accounts(&self.pool_manager.up_tick_array_keys, account_map)?;
         let down_ticks_accounts = self.get_tick_arrays_accounts(&self.pool_manager.down_tick_array_keys, account_map)?;
-        self.pool_manager.update(vec![amm_config_account, mint0_account, mint1_data, tickarray_bitmap_extension_account], up_ticks_accounts, down_ticks_accounts)
+
+        // Snapshot the tick arrays that are actually initialized on-chain
+        // *before* recentering overwrites the key lists with the new target
+        // window -- these are the arrays a swap can assume already exist.
+        // A key merely being in `account_map` isn't enough: the RPC returns
+        // an empty placeholder `Account` for keys that don't exist yet, and
+        // a tick array newly entering the recentered window would otherwise
+        // be mistaken for one that's already there. Anything
+        // `recenter_tick_arrays` adds beyond this confirmed set is
+        // unconfirmed until the next update.
+        let up_confirmed = self
+            .pool_manager
+            .up_tick_array_keys
+            .iter()
+            .copied()
+            .zip(up_ticks_accounts.iter().copied());
+        let down_confirmed = self
+            .pool_manager
+            .down_tick_array_keys
+            .iter()
+            .copied()
+            .zip(down_ticks_accounts.iter().copied());
+        let confirmed_tick_arrays: HashSet<Pubkey> = up_confirmed
+            .chain(down_confirmed)
+            .filter(|(_, account)| self.is_tick_array_initialized(account))
+            .map(|(key, _)| key)
+            .collect();
+
+        self.pool_manager.update(vec![amm_config_account, mint0_account, mint1_data, tickarray_bitmap_extension_account], up_ticks_accounts, down_ticks_accounts)?;
+
+        self.mint0_account = Some(mint0_account.clone());
+        self.mint1_account = Some(mint1_data.clone());
+
+        self.recenter_tick_arrays(tickarray_bitmap_extension_account)?;
+
+        self.known_tick_arrays = confirmed_tick_arrays;
+
+        Ok(())
     }
 
     fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
-        let quote = QuoteCalculator::calculate_quote(
-            quote_params.input_mint,
-            quote_params.output_mint,
-            quote_params.swap_mode == jupiter_amm_interface::SwapMode::ExactIn,
-            quote_params.amount,
-            &self.pool_manager)?;
-        Ok(Quote {
-            fee_pct: quote.fee_pct,
-            in_amount: quote.in_amount,
-            out_amount: quote.out_amount,
-            fee_amount: quote.fee_amount,
-            fee_mint: quote.fee_mint,
-            ..Quote::default()
-        })
-
+        self.quote_with_transfer_fees(quote_params).map(|(quote, _fees)| quote)
     }
 
     fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
         let instruction = InstructionBuilder::build_swap_instruction(&self.pool_manager, swap_params.source_mint, swap_params.destination_mint, swap_params.source_token_account, swap_params.destination_token_account)?;
         let account_metas = instruction.accounts;
+
+        // Any tick array the swap instruction references that wasn't among
+        // the arrays `update()` confirmed exist on-chain is one the swap
+        // would be crossing into for the first time, and needs to be
+        // initialized ahead of the swap.
+        let mut setup_instructions = Vec::new();
+        for account_meta in &account_metas {
+            let key = account_meta.pubkey;
+            let is_tick_array = self.pool_manager.up_tick_array_keys.contains(&key)
+                || self.pool_manager.down_tick_array_keys.contains(&key);
+            if is_tick_array && !self.known_tick_arrays.contains(&key) {
+                setup_instructions.push(InstructionBuilder::build_initialize_tick_array_instruction(
+                    &self.pool_manager,
+                    swap_params.token_transfer_authority,
+                    key,
+                )?);
+            }
+        }
+
+        // Creating the destination ATA is idempotent, so it's safe to always
+        // include it rather than trying to infer whether it already exists
+        // from state this method doesn't have access to.
+        setup_instructions.push(create_associated_token_account_idempotent(
+            &swap_params.token_transfer_authority,
+            &swap_params.token_transfer_authority,
+            &swap_params.destination_mint,
+            &spl_token_2022::id(),
+        ));
+
+        *self.pending_user_setup.lock().unwrap() = Some(AmmUserSetup {
+            instructions: setup_instructions,
+        });
+
         Ok(SwapAndAccountMetas {
             swap: Swap::RaydiumClmmV2,
             account_metas,
@@ -154,7 +500,7 @@ where
     }
 
     fn has_dynamic_accounts(&self) -> bool {
-        false
+        true
     }
 
     fn requires_update_for_reserve_mints(&self) -> bool {
@@ -165,8 +511,8 @@ where
         true
     }
 
-    fn get_user_setup(&self) -> Option<jupiter_amm_interface::AmmUserSetup> {
-        None
+    fn get_user_setup(&self) -> Option<AmmUserSetup> {
+        self.pending_user_setup.lock().unwrap().clone()
     }
 
     fn unidirectional(&self) -> bool {
@@ -180,8 +526,9 @@ where
     fn get_accounts_len(&self) -> usize {
         let base_acounts = 13; //with signer
         let tick_arrsy_bitmap_extension = 1;
-        let tick_array_accounts = NEIGHBORHOOD_SIZE;
-        base_acounts + tick_arrsy_bitmap_extension + tick_array_accounts as usize
+        let tick_array_accounts =
+            Self::tick_arrays_needed_for_spacing(self.pool_manager.pool_state.tick_spacing);
+        base_acounts + tick_arrsy_bitmap_extension + tick_array_accounts
     }
 
     fn underlying_liquidities(&self) -> Option<std::collections::HashSet<Pubkey>> {
@@ -192,3 +539,133 @@ where
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_tick_array_starts_always_includes_current_tick_array() {
+        let tick_spacing: u16 = 60;
+        let tick_current = 12_345;
+        let expected_start_index = TickArrayBitmapExtension::get_array_start_index(tick_current, tick_spacing);
+
+        let bitmap_extension = TickArrayBitmapExtension::default();
+        let tick_array_bitmap = [0u64; 16];
+
+        for zero_for_one in [false, true] {
+            let starts = SwapIoClmmAdapter::walk_tick_array_starts(
+                tick_current,
+                tick_spacing,
+                tick_array_bitmap,
+                &bitmap_extension,
+                zero_for_one,
+            );
+
+            assert_eq!(
+                starts.first().copied(),
+                Some(expected_start_index),
+                "the tick array holding the current tick must always be in the recomputed window (zero_for_one = {zero_for_one})"
+            );
+        }
+    }
+
+    /// Packs a Token-2022 mint with a `TransferFeeConfig` extension set to
+    /// `fee_bps` basis points (capped at `maximum_fee`), active as of epoch 0.
+    fn mint_account_with_transfer_fee(fee_bps: u16, maximum_fee: u64) -> Account {
+        use spl_token_2022::extension::{
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            ExtensionType, StateWithExtensionsMut,
+        };
+        use spl_token_2022::pod::{OptionalNonZeroPubkey, PodU16, PodU64};
+
+        let account_len =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+        let mut data = vec![0u8; account_len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+
+        let fee = TransferFee {
+            epoch: PodU64::from(0u64),
+            maximum_fee: PodU64::from(maximum_fee),
+            transfer_fee_basis_points: PodU16::from(fee_bps),
+        };
+        let extension = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        extension.transfer_fee_config_authority = OptionalNonZeroPubkey::default();
+        extension.withdraw_withheld_authority = OptionalNonZeroPubkey::default();
+        extension.withheld_amount = PodU64::from(0u64);
+        extension.older_transfer_fee = fee;
+        extension.newer_transfer_fee = fee;
+
+        state.base = Mint {
+            mint_authority: Default::default(),
+            supply: 0,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: Default::default(),
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        Account {
+            lamports: 1,
+            data,
+            owner: spl_token_2022::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// An adapter with `mint0` carrying the given transfer fee and `mint1`
+    /// carrying none, enough to exercise the fee-math helpers without a real
+    /// `PoolManager` built from on-chain account data.
+    fn adapter_with_mint0_fee(fee_bps: u16, maximum_fee: u64) -> SwapIoClmmAdapter {
+        let mut pool_manager = PoolManager::default();
+        pool_manager.pool_state.token_mint_0 = Pubkey::new_unique();
+        pool_manager.pool_state.token_mint_1 = Pubkey::new_unique();
+
+        SwapIoClmmAdapter {
+            mint0_account: Some(mint_account_with_transfer_fee(fee_bps, maximum_fee)),
+            mint1_account: None,
+            known_tick_arrays: HashSet::new(),
+            pending_user_setup: Mutex::new(None),
+            pool_manager,
+        }
+    }
+
+    #[test]
+    fn transfer_fee_for_amount_applies_bps_fee_under_the_cap() {
+        let adapter = adapter_with_mint0_fee(100, 1_000_000); // 1%, far above any amount tested
+        let mint0 = adapter.pool_manager.pool_state.token_mint_0;
+
+        let fee = adapter.transfer_fee_for_amount(mint0, 0, 10_000).unwrap();
+        assert_eq!(fee, 100);
+    }
+
+    #[test]
+    fn transfer_fee_for_amount_respects_the_maximum_fee_cap() {
+        let adapter = adapter_with_mint0_fee(100, 50); // 1%, capped at 50
+        let mint0 = adapter.pool_manager.pool_state.token_mint_0;
+
+        let fee = adapter.transfer_fee_for_amount(mint0, 0, 10_000).unwrap();
+        assert_eq!(fee, 50);
+    }
+
+    #[test]
+    fn transfer_fee_for_amount_is_zero_for_a_mint_without_the_extension() {
+        let adapter = adapter_with_mint0_fee(100, 1_000_000);
+        let mint1 = adapter.pool_manager.pool_state.token_mint_1;
+
+        let fee = adapter.transfer_fee_for_amount(mint1, 0, 10_000).unwrap();
+        assert_eq!(fee, 0);
+    }
+
+    #[test]
+    fn gross_up_for_transfer_fee_nets_back_to_the_target_amount() {
+        let adapter = adapter_with_mint0_fee(100, 1_000_000); // 1%, far above any amount tested
+        let mint0 = adapter.pool_manager.pool_state.token_mint_0;
+
+        let gross_amount = adapter.gross_up_for_transfer_fee(mint0, 0, 9_900).unwrap();
+        let fee = adapter.transfer_fee_for_amount(mint0, 0, gross_amount).unwrap();
+        assert_eq!(gross_amount - fee, 9_900);
+    }
+}
@@ -0,0 +1,126 @@
+//! Property-based fuzz harness for `QuoteCalculator` and `InstructionBuilder`.
+//!
+//! Gated behind the `fuzz` feature so the `arbitrary`/`honggfuzz` dependencies
+//! never leak into normal builds; driven by the `hfuzz_targets/adapter_quote`
+//! target via `cargo hfuzz run adapter_quote`.
+
+use arbitrary::Arbitrary;
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::states::tick_math::{MAX_TICK, MIN_TICK};
+use swap_io_clmm_rust_sdk::{instruction::InstructionBuilder, pool::PoolManager, quote::QuoteCalculator};
+
+/// A synthetically generated pool state covering the inputs
+/// `calculate_quote` and `build_swap_instruction` are sensitive to: price,
+/// liquidity, fee config, tick spacing, and tick-array contents.
+#[derive(Debug, Arbitrary)]
+pub struct FuzzPoolState {
+    pub sqrt_price_x64: u128,
+    pub liquidity: u128,
+    pub tick_spacing: u16,
+    pub fee_rate: u32,
+    pub tick_current: i32,
+    pub tick_arrays: Vec<FuzzTickArray>,
+}
+
+#[derive(Debug, Arbitrary)]
+pub struct FuzzTickArray {
+    pub start_tick_index: i32,
+    pub ticks: Vec<FuzzTick>,
+}
+
+#[derive(Debug, Arbitrary)]
+pub struct FuzzTick {
+    pub tick: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+}
+
+/// One fuzz case: a pool state plus the swap to attempt against it. Mint
+/// direction is randomized too, so the mint-ordering branch gets exercised
+/// symmetrically.
+#[derive(Debug, Arbitrary)]
+pub struct FuzzSwap {
+    pub pool: FuzzPoolState,
+    pub amount: u64,
+    pub exact_in: bool,
+    pub swap_mint0_to_mint1: bool,
+}
+
+/// Builds a `PoolManager` straight from fuzzed field values instead of
+/// through a constructor keyed to `FuzzPoolState` -- `PoolManager` lives in
+/// `swap-io-clmm-rust-sdk`, which this crate depends on, so a constructor
+/// over there taking a type defined here would need the dependency to run
+/// the other way too. `PoolManager`'s fields used for quoting and
+/// instruction-building are already assigned directly elsewhere in this
+/// crate (see `recenter_tick_arrays`), so this does the same, starting from
+/// `PoolManager::default()`.
+///
+/// This only wires up the top-level price/liquidity/tick-spacing state;
+/// `pool.tick_arrays` isn't injected yet, since doing that correctly needs
+/// whatever internal tick-array storage `PoolManager::update` populates,
+/// which isn't part of its public surface. Fuzz cases exercise in-range
+/// quoting and instruction-building, not tick-crossing, until the SDK
+/// exposes a supported way to seed tick-array data.
+fn build_pool_manager(pool: &FuzzPoolState) -> PoolManager {
+    let mut pool_manager = PoolManager::default();
+    pool_manager.pool_state.amm_config = Pubkey::new_unique();
+    pool_manager.pool_state.token_mint_0 = Pubkey::new_unique();
+    pool_manager.pool_state.token_mint_1 = Pubkey::new_unique();
+    pool_manager.pool_state.tick_spacing = pool.tick_spacing.max(1);
+    pool_manager.pool_state.tick_current = pool.tick_current.clamp(MIN_TICK, MAX_TICK);
+    pool_manager.pool_state.sqrt_price_x64 = pool.sqrt_price_x64;
+    pool_manager.pool_state.liquidity = pool.liquidity;
+    pool_manager
+}
+
+/// Checks the invariants a single fuzz input must satisfy and returns a
+/// descriptive error (rather than panicking directly); the fuzz target panics
+/// with that message for honggfuzz to capture.
+pub fn check_invariants(input: FuzzSwap) -> Result<(), String> {
+    let pool_manager = build_pool_manager(&input.pool);
+
+    let (mint_in, mint_out) = if input.swap_mint0_to_mint1 {
+        (pool_manager.pool_state.token_mint_0, pool_manager.pool_state.token_mint_1)
+    } else {
+        (pool_manager.pool_state.token_mint_1, pool_manager.pool_state.token_mint_0)
+    };
+
+    let quote = QuoteCalculator::calculate_quote(mint_in, mint_out, input.exact_in, input.amount, &pool_manager)
+        .map_err(|e| format!("calculate_quote failed: {e}"))?;
+
+    if quote.fee_amount > quote.in_amount {
+        return Err(format!(
+            "fee_amount {} exceeds in_amount {}",
+            quote.fee_amount, quote.in_amount
+        ));
+    }
+
+    if input.amount > 0 && quote.in_amount > 0 && quote.out_amount == 0 {
+        return Err("non-zero input produced a zero out_amount (negative effective price)".to_string());
+    }
+
+    // Round trip: an ExactIn quote's out_amount fed back through ExactOut
+    // (and vice versa) should recover an input within one unit of the
+    // original, plus rounding introduced by the pool fee.
+    let round_trip_exact_in = !input.exact_in;
+    let round_trip_amount = if input.exact_in { quote.out_amount } else { quote.in_amount };
+    let round_trip = QuoteCalculator::calculate_quote(mint_in, mint_out, round_trip_exact_in, round_trip_amount, &pool_manager)
+        .map_err(|e| format!("round-trip calculate_quote failed: {e}"))?;
+    let round_trip_result = if input.exact_in { round_trip.in_amount } else { round_trip.out_amount };
+    let diff = round_trip_result.abs_diff(input.amount);
+    let tolerance = quote.fee_amount + 1;
+    if diff > tolerance {
+        return Err(format!(
+            "round trip diverged by {diff}, expected at most fee_amount + 1 ({tolerance})"
+        ));
+    }
+
+    // Instruction building must succeed whenever a quote did, and must not
+    // panic on the generated pool state.
+    let source = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    InstructionBuilder::build_swap_instruction(&pool_manager, mint_in, mint_out, source, destination)
+        .map_err(|e| format!("build_swap_instruction failed: {e}"))?;
+
+    Ok(())
+}